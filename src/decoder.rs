@@ -0,0 +1,620 @@
+//! TOON decoder — parses TOON text back into [`serde_json::Value`].
+//!
+//! The parser is line/indent-aware: each physical line is classified by its
+//! indentation depth (in units of [`ToonOptions::indent`]) and then matched
+//! against the handful of shapes [`crate::encode_toon`] emits. Quoted
+//! scalars never contain a real newline (the encoder escapes `\n`), so
+//! splitting on `\n` up front is safe.
+
+use std::fmt;
+
+use serde_json::{Map, Number, Value};
+
+use crate::ToonOptions;
+
+/// An error produced while decoding TOON text, with a 1-based line/column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn err(line: &Line, message: impl Into<String>) -> DecodeError {
+    DecodeError { line: line.number, column: line.col, message: message.into() }
+}
+
+struct Line<'a> {
+    number: usize,
+    col: usize,
+    depth: usize,
+    content: &'a str,
+}
+
+/// Decode a TOON document into a [`Value`], reversing [`crate::encode_toon`].
+pub fn decode_toon(input: &str, opts: &ToonOptions) -> Result<Value, DecodeError> {
+    let lines = tokenize(input, opts.indent)?;
+
+    let Some(first) = lines.first() else {
+        return Ok(Value::Object(Map::new()));
+    };
+    if first.depth != 0 {
+        return Err(err(first, "document must start at indentation level 0"));
+    }
+
+    if let Ok(header) = parse_header(first.content, first) {
+        if header.key.is_empty() && header.array_len.is_some() {
+            let (value, next) = decode_array_value(&lines, 0, 0, &header, opts)?;
+            return finish(&lines, next, value);
+        }
+        if !header.key.is_empty() {
+            let (value, next) = decode_object(&lines, 0, 0, opts)?;
+            return finish(&lines, next, value);
+        }
+    }
+
+    if lines.len() != 1 {
+        return Err(err(&lines[1], "unexpected trailing content after scalar root"));
+    }
+    Ok(parse_scalar(first.content))
+}
+
+fn finish(lines: &[Line], next: usize, value: Value) -> Result<Value, DecodeError> {
+    if next != lines.len() {
+        return Err(err(&lines[next], "unexpected trailing content"));
+    }
+    Ok(value)
+}
+
+fn tokenize(input: &str, indent: usize) -> Result<Vec<Line<'_>>, DecodeError> {
+    let mut lines = Vec::new();
+    for (i, raw) in input.lines().enumerate() {
+        if raw.trim().is_empty() {
+            continue;
+        }
+        let spaces = raw.len() - raw.trim_start_matches(' ').len();
+        let content = &raw[spaces..];
+        let depth = if indent == 0 {
+            0
+        } else {
+            if spaces % indent != 0 {
+                return Err(DecodeError {
+                    line: i + 1,
+                    column: spaces + 1,
+                    message: format!("indentation of {spaces} spaces is not a multiple of {indent}"),
+                });
+            }
+            spaces / indent
+        };
+        lines.push(Line { number: i + 1, col: spaces + 1, depth, content });
+    }
+    Ok(lines)
+}
+
+/// A parsed `key[N<sym>]{f1,f2}: value` header line, before its body (if any)
+/// is decoded.
+struct ParsedHeader<'a> {
+    key: String,
+    array_len: Option<usize>,
+    delim_sym: String,
+    fields: Option<Vec<String>>,
+    value: &'a str,
+}
+
+fn delim_from_symbol(sym: &str) -> char {
+    match sym {
+        "\t" => '\t',
+        "|" => '|',
+        _ => ',',
+    }
+}
+
+/// Scans a `"..."`-quoted run starting just after the opening quote,
+/// decoding escapes. Returns the decoded string and the remainder after the
+/// closing quote, or `None` if the quote is never closed.
+fn scan_quoted(s: &str) -> Option<(String, &str)> {
+    let mut result = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                let (_, esc) = chars.next()?;
+                result.push(match esc {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    other => other,
+                });
+            }
+            '"' => return Some((result, &s[i + c.len_utf8()..])),
+            _ => result.push(c),
+        }
+    }
+    None
+}
+
+fn parse_key<'a>(content: &'a str, line: &Line) -> Result<(String, &'a str), DecodeError> {
+    if let Some(rest) = content.strip_prefix('"') {
+        scan_quoted(rest).ok_or_else(|| err(line, "unterminated quoted key"))
+    } else {
+        let end = content.find(['[', ':']).unwrap_or(content.len());
+        Ok((content[..end].to_string(), &content[end..]))
+    }
+}
+
+fn unquote_field(f: &str, line: &Line) -> Result<String, DecodeError> {
+    if let Some(rest) = f.strip_prefix('"') {
+        scan_quoted(rest)
+            .filter(|(_, after)| after.is_empty())
+            .map(|(s, _)| s)
+            .ok_or_else(|| err(line, "unterminated quoted field name"))
+    } else {
+        Ok(f.to_string())
+    }
+}
+
+/// Finds the byte index of the first unquoted occurrence of `target`,
+/// ignoring occurrences inside `"..."` runs (as `split_delim` does for
+/// delimiters).
+fn find_unquoted(s: &str, target: char) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '\\' if in_quotes => {
+                chars.next();
+            }
+            c if c == target && !in_quotes => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits `s` on `delim`, ignoring delimiters inside `"..."` runs.
+fn split_delim(s: &str, delim: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '\\' if in_quotes => {
+                chars.next();
+            }
+            c if c == delim && !in_quotes => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn parse_header<'a>(content: &'a str, line: &Line) -> Result<ParsedHeader<'a>, DecodeError> {
+    let (key, mut rest) = parse_key(content, line)?;
+    let mut array_len = None;
+    let mut delim_sym = String::new();
+    let mut fields = None;
+
+    if let Some(r) = rest.strip_prefix('[') {
+        let end = r.find(']').ok_or_else(|| err(line, "unterminated '[' in array header"))?;
+        let spec = &r[..end];
+        let digit_end = spec.find(|c: char| !c.is_ascii_digit()).unwrap_or(spec.len());
+        let (digits, sym) = spec.split_at(digit_end);
+        if !matches!(sym, "" | "\t" | "|") {
+            return Err(err(line, format!("unrecognized delimiter symbol '{sym}'")));
+        }
+        array_len = Some(digits.parse::<usize>().map_err(|_| err(line, "expected array length"))?);
+        delim_sym = sym.to_string();
+        rest = &r[end + 1..];
+    }
+
+    if let Some(r) = rest.strip_prefix('{') {
+        // Quote-aware, like `split_delim` below, so a field name containing
+        // a literal '}' (forced to be quoted by `needs_quoting`) doesn't
+        // truncate the field list early.
+        let end = find_unquoted(r, '}').ok_or_else(|| err(line, "unterminated '{' in tabular header"))?;
+        let delim_char = delim_from_symbol(&delim_sym);
+        let names = split_delim(&r[..end], delim_char)
+            .into_iter()
+            .map(|f| unquote_field(f, line))
+            .collect::<Result<Vec<_>, _>>()?;
+        fields = Some(names);
+        rest = &r[end + 1..];
+    }
+
+    let rest = rest.strip_prefix(':').ok_or_else(|| err(line, "expected ':'"))?;
+    let value = rest.strip_prefix(' ').unwrap_or(rest);
+    Ok(ParsedHeader { key, array_len, delim_sym, fields, value })
+}
+
+fn parse_scalar(tok: &str) -> Value {
+    if let Some(rest) = tok.strip_prefix('"') {
+        return match scan_quoted(rest) {
+            Some((s, _)) => Value::String(s),
+            None => Value::String(tok.to_string()),
+        };
+    }
+    match tok {
+        "null" => return Value::Null,
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+    if let Ok(i) = tok.parse::<i64>() {
+        return Value::Number(i.into());
+    }
+    if let Ok(u) = tok.parse::<u64>() {
+        return Value::Number(u.into());
+    }
+    if crate::looks_like_number(tok) {
+        // Beyond i64/u64 range, or genuinely fractional: with our
+        // `arbitrary_precision` feature on (forwarding to
+        // `serde_json/arbitrary_precision`), `Number` can hold the token
+        // exactly, which is what makes `ToonOptions::raw_numbers` output
+        // round-trip losslessly instead of through an approximate `f64`.
+        if let Some(n) = exact_number(tok) {
+            return Value::Number(n);
+        }
+    }
+    if let Ok(f) = tok.parse::<f64>() {
+        if let Some(n) = Number::from_f64(f) {
+            return Value::Number(n);
+        }
+    }
+    Value::String(tok.to_string())
+}
+
+#[cfg(feature = "arbitrary_precision")]
+fn exact_number(tok: &str) -> Option<Number> {
+    tok.parse::<Number>().ok()
+}
+
+#[cfg(not(feature = "arbitrary_precision"))]
+fn exact_number(_tok: &str) -> Option<Number> {
+    None
+}
+
+/// Inserts `value` under `key`, re-expanding a dotted key (produced by
+/// [`crate::KeyFolding::Safe`]) into nested single-key objects.
+fn insert_key(map: &mut Map<String, Value>, key: &str, value: Value) {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            map.insert(head.to_string(), nest_dotted(rest, value));
+        }
+        None => {
+            map.insert(key.to_string(), value);
+        }
+    }
+}
+
+fn nest_dotted(key: &str, value: Value) -> Value {
+    let mut map = Map::new();
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            map.insert(head.to_string(), nest_dotted(rest, value));
+        }
+        None => {
+            map.insert(key.to_string(), value);
+        }
+    }
+    Value::Object(map)
+}
+
+/// Parses the header at `lines[idx]` (whose raw content is `content` — the
+/// line itself for normal fields, or the text after `"- "` for a list item
+/// that embeds its first field) and decodes its value, recursing into a
+/// nested block at `depth + 1` when the header has no inline value.
+fn decode_field_with_content<'a>(
+    lines: &[Line<'a>],
+    idx: usize,
+    depth: usize,
+    content: &'a str,
+    opts: &ToonOptions,
+) -> Result<(String, Value, usize), DecodeError> {
+    let header = parse_header(content, &lines[idx])?;
+
+    if header.array_len.is_some() {
+        let (value, next) = decode_array_value(lines, idx, depth, &header, opts)?;
+        return Ok((header.key, value, next));
+    }
+
+    if header.value.is_empty() {
+        if lines.get(idx + 1).is_some_and(|l| l.depth == depth + 1) {
+            let (value, next) = decode_object(lines, idx + 1, depth + 1, opts)?;
+            return Ok((header.key, value, next));
+        }
+        return Ok((header.key, Value::Object(Map::new()), idx + 1));
+    }
+
+    Ok((header.key, parse_scalar(header.value), idx + 1))
+}
+
+fn decode_object(lines: &[Line], mut idx: usize, depth: usize, opts: &ToonOptions) -> Result<(Value, usize), DecodeError> {
+    let mut map = Map::new();
+    while idx < lines.len() && lines[idx].depth == depth {
+        let content = lines[idx].content;
+        let (key, value, next) = decode_field_with_content(lines, idx, depth, content, opts)?;
+        insert_key(&mut map, &key, value);
+        idx = next;
+    }
+    Ok((Value::Object(map), idx))
+}
+
+fn decode_array_value(
+    lines: &[Line],
+    idx: usize,
+    depth: usize,
+    header: &ParsedHeader,
+    opts: &ToonOptions,
+) -> Result<(Value, usize), DecodeError> {
+    let len = header.array_len.expect("decode_array_value requires an array header");
+    let delim_char = delim_from_symbol(&header.delim_sym);
+
+    if len == 0 {
+        return Ok((Value::Array(Vec::new()), idx + 1));
+    }
+
+    if let Some(fields) = &header.fields {
+        let mut arr = Vec::with_capacity(len);
+        let mut next = idx + 1;
+        for _ in 0..len {
+            let row = lines
+                .get(next)
+                .filter(|l| l.depth == depth + 1)
+                .ok_or_else(|| err(&lines[idx], format!("expected {len} tabular rows, found fewer")))?;
+            let parts = split_delim(row.content, delim_char);
+            if parts.len() != fields.len() {
+                return Err(err(row, format!("expected {} tabular columns, found {}", fields.len(), parts.len())));
+            }
+            let mut obj = Map::new();
+            for (f, v) in fields.iter().zip(parts.iter()) {
+                obj.insert(f.clone(), parse_scalar(v));
+            }
+            arr.push(Value::Object(obj));
+            next += 1;
+        }
+        return Ok((Value::Array(arr), next));
+    }
+
+    if !header.value.is_empty() {
+        let parts = split_delim(header.value, delim_char);
+        if parts.len() != len {
+            return Err(err(
+                &lines[idx],
+                format!("array declared length {len} does not match {} inline elements", parts.len()),
+            ));
+        }
+        return Ok((Value::Array(parts.iter().map(|p| parse_scalar(p)).collect()), idx + 1));
+    }
+
+    let mut arr = Vec::with_capacity(len);
+    let mut next = idx + 1;
+    for _ in 0..len {
+        let item_line = lines
+            .get(next)
+            .filter(|l| l.depth == depth + 1)
+            .ok_or_else(|| err(&lines[idx], format!("expected {len} list items, found fewer")))?;
+        if item_line.content != "-" && !item_line.content.starts_with("- ") {
+            return Err(err(item_line, "expected list item starting with '-'"));
+        }
+        let (value, n) = decode_list_item(lines, next, depth + 1, opts)?;
+        arr.push(value);
+        next = n;
+    }
+    Ok((Value::Array(arr), next))
+}
+
+#[derive(PartialEq)]
+enum ItemKind {
+    Scalar,
+    Header,
+}
+
+/// Distinguishes a plain scalar list item (`- Alice`) from one that embeds a
+/// field or array header (`- name: Alice`, `- [2]: 1,2`): a bare (unquoted)
+/// scalar can never contain `:` or start with `[`, since [`crate::needs_quoting`]
+/// would have forced it to be quoted.
+fn classify_item_content(content: &str) -> ItemKind {
+    if let Some(rest) = content.strip_prefix('"') {
+        return match scan_quoted(rest) {
+            Some((_, after)) if after.starts_with('[') || after.starts_with(':') => ItemKind::Header,
+            _ => ItemKind::Scalar,
+        };
+    }
+    if content.starts_with('[') || content.contains(':') {
+        ItemKind::Header
+    } else {
+        ItemKind::Scalar
+    }
+}
+
+fn decode_list_item(lines: &[Line], idx: usize, depth: usize, opts: &ToonOptions) -> Result<(Value, usize), DecodeError> {
+    let line = &lines[idx];
+    if line.content == "-" {
+        return Ok((Value::Object(Map::new()), idx + 1));
+    }
+    let rest = line
+        .content
+        .strip_prefix("- ")
+        .ok_or_else(|| err(line, "expected list item starting with '-'"))?;
+
+    if classify_item_content(rest) == ItemKind::Scalar {
+        return Ok((parse_scalar(rest), idx + 1));
+    }
+
+    // The encoder writes an embedded first field's nested block one level
+    // below its sibling fields (which sit at `depth + 1`), since `"- "`
+    // takes the place of the sibling indent; a bare nested array item
+    // (`- [N]: ...`) keeps its elements directly below the hyphen line.
+    let field_depth = if parse_header(rest, line)?.key.is_empty() { depth } else { depth + 1 };
+    let (key, value, mut next) = decode_field_with_content(lines, idx, field_depth, rest, opts)?;
+    if key.is_empty() {
+        // A nested array item (`- [N]: ...`) rather than an object field.
+        return Ok((value, next));
+    }
+
+    let mut map = Map::new();
+    insert_key(&mut map, &key, value);
+    while next < lines.len() && lines[next].depth == depth + 1 {
+        let content = lines[next].content;
+        let (k, v, n) = decode_field_with_content(lines, next, depth + 1, content, opts)?;
+        insert_key(&mut map, &k, v);
+        next = n;
+    }
+    Ok((Value::Object(map), next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode_toon;
+    use serde_json::json;
+
+    fn roundtrip(value: Value, opts: &ToonOptions) {
+        let encoded = encode_toon(&value, opts);
+        let decoded = decode_toon(&encoded, opts).expect("decode should succeed");
+        assert_eq!(decoded, value, "round-trip mismatch for TOON:\n{encoded}");
+    }
+
+    #[test]
+    fn test_decode_simple_object() {
+        let v = decode_toon("name: Alice\nage: 30", &ToonOptions::default()).unwrap();
+        assert_eq!(v, json!({"name": "Alice", "age": 30}));
+    }
+
+    #[test]
+    fn test_decode_nested_object() {
+        let v = decode_toon("user:\n  name: Alice\n  age: 30", &ToonOptions::default()).unwrap();
+        assert_eq!(v, json!({"user": {"name": "Alice", "age": 30}}));
+    }
+
+    #[test]
+    fn test_decode_inline_array() {
+        let v = decode_toon("tags[3]: a,b,c", &ToonOptions::default()).unwrap();
+        assert_eq!(v, json!({"tags": ["a", "b", "c"]}));
+    }
+
+    #[test]
+    fn test_decode_tabular_array() {
+        let v = decode_toon("users[2]{id,name}:\n  1,Alice\n  2,Bob", &ToonOptions::default()).unwrap();
+        assert_eq!(v, json!({"users": [{"id": 1, "name": "Alice"}, {"id": 2, "name": "Bob"}]}));
+    }
+
+    #[test]
+    fn test_decode_expanded_list() {
+        let toon = "items[2]:\n  - a: 1\n    b: 2\n  - a: 3\n    b: 4";
+        let v = decode_toon(toon, &ToonOptions::default()).unwrap();
+        assert_eq!(v, json!({"items": [{"a": 1, "b": 2}, {"a": 3, "b": 4}]}));
+    }
+
+    #[test]
+    fn test_decode_key_folding() {
+        let opts = ToonOptions { key_folding: crate::KeyFolding::Safe, ..Default::default() };
+        let v = decode_toon("data.metadata.name: test", &opts).unwrap();
+        assert_eq!(v, json!({"data": {"metadata": {"name": "test"}}}));
+    }
+
+    #[test]
+    fn test_decode_quoted_scalars() {
+        let v = decode_toon("x: \"true\"\ny: \"\"\nz: \"a,b\"", &ToonOptions::default()).unwrap();
+        assert_eq!(v, json!({"x": "true", "y": "", "z": "a,b"}));
+    }
+
+    #[test]
+    fn test_decode_root_array() {
+        let v = decode_toon("[3]: 1,2,3", &ToonOptions::default()).unwrap();
+        assert_eq!(v, json!([1, 2, 3]));
+    }
+
+    #[test]
+    fn test_decode_empty_object() {
+        let v = decode_toon("x:", &ToonOptions::default()).unwrap();
+        assert_eq!(v, json!({"x": {}}));
+    }
+
+    #[test]
+    fn test_decode_empty_array() {
+        let v = decode_toon("x[0]:", &ToonOptions::default()).unwrap();
+        assert_eq!(v, json!({"x": []}));
+    }
+
+    #[test]
+    fn test_decode_empty_input_is_empty_object() {
+        assert_eq!(decode_toon("", &ToonOptions::default()).unwrap(), json!({}));
+    }
+
+    #[test]
+    fn test_decode_root_scalar() {
+        assert_eq!(decode_toon("42", &ToonOptions::default()).unwrap(), json!(42));
+        assert_eq!(decode_toon("\"hi\"", &ToonOptions::default()).unwrap(), json!("hi"));
+    }
+
+    #[test]
+    fn test_decode_array_length_mismatch() {
+        let result = decode_toon("tags[3]: a,b", &ToonOptions::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_decode_bad_indentation() {
+        let result = decode_toon("user:\n   name: Alice", &ToonOptions::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_roundtrip_various_shapes() {
+        roundtrip(
+            json!({
+                "name": "Alice",
+                "tags": ["a", "b", "c"],
+                "nested": {"x": 1, "y": [1, 2, {"z": true}]},
+                "users": [{"id": 1, "name": "Bob"}, {"id": 2, "name": "Eve"}],
+                "empty_obj": {},
+                "empty_arr": [],
+            }),
+            &ToonOptions::default(),
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_tabular_field_name_containing_brace() {
+        // `needs_quoting` forces a `}`-containing column name to be quoted;
+        // the header scan must be quote-aware to find the real closing `}`
+        // instead of the one inside the quoted field name.
+        roundtrip(
+            json!({"items": [{"a}b": 1, "c": 2}, {"a}b": 3, "c": 4}]}),
+            &ToonOptions::default(),
+        );
+    }
+
+    // Only meaningful once the crate's own `arbitrary_precision` feature is
+    // wired up in Cargo.toml; without it, numbers beyond i64/u64 range or
+    // with more precision than `f64` holds were never losslessly
+    // representable in the first place, so `roundtrip` would fail on the
+    // `f64`-approximation it falls back to.
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_roundtrip_raw_numbers_beyond_f64_precision() {
+        let opts = ToonOptions { raw_numbers: true, ..ToonOptions::default() };
+        // Built from source text rather than Rust float literals so the
+        // precision beyond what `f64` holds survives into the `Value`.
+        let value: Value =
+            serde_json::from_str(r#"{"big":10000000000000000001,"pi":3.141592653589793238}"#)
+                .unwrap();
+        roundtrip(value, &opts);
+    }
+}
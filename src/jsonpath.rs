@@ -0,0 +1,632 @@
+//! A focused JSONPath evaluator used to select a subtree (or set of nodes)
+//! out of a [`Value`] before encoding, so callers can convert just
+//! `$.store.book[*].title` of a large document instead of the whole thing.
+//!
+//! This is not a full JSONPath implementation — it covers the operators
+//! requests for this feature actually need: root `$`, child `.name` and
+//! `['name']`, recursive descent `..`, wildcard `*`, array index `[n]`
+//! (negative indices count from the end), slice `[start:end:step]`, and
+//! predicate filters `[?(@.field > 3)]`.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::ToonOptions;
+
+/// An error produced while parsing or evaluating a JSONPath expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPathError {
+    /// Byte offset into the expression where the error was detected.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid JSONPath expression at byte {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for JsonPathError {}
+
+/// Apply `opts.select` (if set) to `value`, returning the subtree to encode.
+///
+/// A single match is returned as-is; multiple matches are wrapped into a
+/// JSON array so the existing inline/tabular array encoding logic applies.
+/// Returns a borrowed [`Cow`] when there's nothing to select, so the common
+/// case (no `select`) doesn't pay for a clone.
+pub fn project<'a>(value: &'a Value, opts: &ToonOptions) -> Result<Cow<'a, Value>, JsonPathError> {
+    match &opts.select {
+        None => Ok(Cow::Borrowed(value)),
+        Some(expr) => {
+            let matches = select(value, expr)?;
+            Ok(Cow::Owned(match matches.as_slice() {
+                [single] => (*single).clone(),
+                multiple => Value::Array(multiple.iter().map(|v| (*v).clone()).collect()),
+            }))
+        }
+    }
+}
+
+/// Evaluate `expr` against `value`, returning every matching node in
+/// document order.
+pub fn select<'a>(value: &'a Value, expr: &str) -> Result<Vec<&'a Value>, JsonPathError> {
+    let segments = parse(expr)?;
+    let mut current = vec![value];
+    for segment in &segments {
+        current = apply_segment(&current, segment);
+    }
+    Ok(current)
+}
+
+enum Segment {
+    /// A normal step (`.name`, `['name']`, `[*]`, `[n]`, `[a:b:c]`, `[?(...)]`).
+    Select(Selector),
+    /// `..` followed by a selector, applied to every descendant node.
+    Descend(Selector),
+}
+
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice { start: Option<i64>, end: Option<i64>, step: i64 },
+    Filter(FilterExpr),
+}
+
+struct FilterExpr {
+    field: String,
+    test: FilterTest,
+}
+
+enum FilterTest {
+    /// `@.field` with no comparator: the field is present and truthy.
+    Truthy,
+    Compare(CompareOp, FilterValue),
+}
+
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+enum FilterValue {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Null,
+}
+
+struct Cursor<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(s: &'a str) -> Self {
+        Self { s, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn err(&self, message: impl Into<String>) -> JsonPathError {
+        JsonPathError { position: self.pos, message: message.into() }
+    }
+}
+
+fn parse(expr: &str) -> Result<Vec<Segment>, JsonPathError> {
+    let mut cur = Cursor::new(expr);
+    if !cur.eat('$') {
+        return Err(cur.err("expression must start with '$'"));
+    }
+
+    let mut segments = Vec::new();
+    while cur.peek().is_some() {
+        if cur.rest().starts_with("..") {
+            cur.pos += 2;
+            segments.push(Segment::Descend(parse_selector_after_descend(&mut cur)?));
+        } else if cur.eat('.') {
+            segments.push(Segment::Select(parse_dot_selector(&mut cur)?));
+        } else if cur.peek() == Some('[') {
+            segments.push(Segment::Select(parse_bracket_selector(&mut cur)?));
+        } else {
+            return Err(cur.err(format!("unexpected character '{}'", cur.peek().unwrap())));
+        }
+    }
+    Ok(segments)
+}
+
+fn parse_dot_selector(cur: &mut Cursor) -> Result<Selector, JsonPathError> {
+    if cur.eat('*') {
+        return Ok(Selector::Wildcard);
+    }
+    Ok(Selector::Name(parse_identifier(cur)?))
+}
+
+fn parse_selector_after_descend(cur: &mut Cursor) -> Result<Selector, JsonPathError> {
+    if cur.peek() == Some('[') {
+        return parse_bracket_selector(cur);
+    }
+    if cur.eat('*') {
+        return Ok(Selector::Wildcard);
+    }
+    Ok(Selector::Name(parse_identifier(cur)?))
+}
+
+fn parse_identifier(cur: &mut Cursor) -> Result<String, JsonPathError> {
+    let start = cur.pos;
+    while let Some(c) = cur.peek() {
+        if c.is_alphanumeric() || c == '_' {
+            cur.bump();
+        } else {
+            break;
+        }
+    }
+    if cur.pos == start {
+        return Err(cur.err("expected an identifier"));
+    }
+    Ok(cur.s[start..cur.pos].to_string())
+}
+
+fn parse_bracket_selector(cur: &mut Cursor) -> Result<Selector, JsonPathError> {
+    cur.eat('[');
+    let selector = if cur.eat('*') {
+        Selector::Wildcard
+    } else if matches!(cur.peek(), Some('\'') | Some('"')) {
+        Selector::Name(parse_quoted_string(cur)?)
+    } else if cur.eat('?') {
+        parse_filter(cur)?
+    } else {
+        parse_index_or_slice(cur)?
+    };
+    if !cur.eat(']') {
+        return Err(cur.err("expected ']'"));
+    }
+    Ok(selector)
+}
+
+fn parse_quoted_string(cur: &mut Cursor) -> Result<String, JsonPathError> {
+    let quote = cur.bump().ok_or_else(|| cur.err("expected a quoted string"))?;
+    let start = cur.pos;
+    while let Some(c) = cur.peek() {
+        if c == quote {
+            let s = cur.s[start..cur.pos].to_string();
+            cur.bump();
+            return Ok(s);
+        }
+        cur.bump();
+    }
+    Err(cur.err("unterminated quoted string"))
+}
+
+fn parse_signed_int(cur: &mut Cursor) -> Option<i64> {
+    let start = cur.pos;
+    if cur.peek() == Some('-') {
+        cur.bump();
+    }
+    while let Some(c) = cur.peek() {
+        if c.is_ascii_digit() {
+            cur.bump();
+        } else {
+            break;
+        }
+    }
+    if cur.pos == start {
+        return None;
+    }
+    let text = &cur.s[start..cur.pos];
+    if text == "-" {
+        cur.pos = start;
+        return None;
+    }
+    text.parse::<i64>().ok()
+}
+
+fn parse_index_or_slice(cur: &mut Cursor) -> Result<Selector, JsonPathError> {
+    let first = parse_signed_int(cur);
+    if cur.eat(':') {
+        let end = parse_signed_int(cur);
+        let step = if cur.eat(':') { parse_signed_int(cur).unwrap_or(1) } else { 1 };
+        if step == 0 {
+            return Err(cur.err("slice step cannot be 0"));
+        }
+        return Ok(Selector::Slice { start: first, end, step });
+    }
+    first.map(Selector::Index).ok_or_else(|| cur.err("expected an index or slice"))
+}
+
+fn skip_ws(cur: &mut Cursor) {
+    while matches!(cur.peek(), Some(c) if c.is_whitespace()) {
+        cur.bump();
+    }
+}
+
+fn parse_filter(cur: &mut Cursor) -> Result<Selector, JsonPathError> {
+    skip_ws(cur);
+    if !cur.eat('(') {
+        return Err(cur.err("expected '(' after '?'"));
+    }
+    skip_ws(cur);
+    if !cur.eat('@') {
+        return Err(cur.err("expected '@' in filter expression"));
+    }
+    let field = parse_filter_field(cur)?;
+    skip_ws(cur);
+
+    let test = if let Some(op) = try_parse_compare_op(cur) {
+        skip_ws(cur);
+        FilterTest::Compare(op, parse_filter_value(cur)?)
+    } else {
+        FilterTest::Truthy
+    };
+
+    skip_ws(cur);
+    if !cur.eat(')') {
+        return Err(cur.err("expected ')' to close filter"));
+    }
+    Ok(Selector::Filter(FilterExpr { field, test }))
+}
+
+fn parse_filter_field(cur: &mut Cursor) -> Result<String, JsonPathError> {
+    if cur.eat('.') {
+        parse_identifier(cur)
+    } else if cur.eat('[') {
+        let name = parse_quoted_string(cur)?;
+        if !cur.eat(']') {
+            return Err(cur.err("expected ']'"));
+        }
+        Ok(name)
+    } else {
+        Err(cur.err("expected '.field' or ['field'] after '@'"))
+    }
+}
+
+fn try_parse_compare_op(cur: &mut Cursor) -> Option<CompareOp> {
+    let (op, len) = if cur.rest().starts_with(">=") {
+        (CompareOp::Ge, 2)
+    } else if cur.rest().starts_with("<=") {
+        (CompareOp::Le, 2)
+    } else if cur.rest().starts_with("==") {
+        (CompareOp::Eq, 2)
+    } else if cur.rest().starts_with("!=") {
+        (CompareOp::Ne, 2)
+    } else if cur.rest().starts_with('>') {
+        (CompareOp::Gt, 1)
+    } else if cur.rest().starts_with('<') {
+        (CompareOp::Lt, 1)
+    } else {
+        return None;
+    };
+    cur.pos += len;
+    Some(op)
+}
+
+fn parse_filter_value(cur: &mut Cursor) -> Result<FilterValue, JsonPathError> {
+    if matches!(cur.peek(), Some('\'') | Some('"')) {
+        return Ok(FilterValue::String(parse_quoted_string(cur)?));
+    }
+    if cur.rest().starts_with("true") {
+        cur.pos += 4;
+        return Ok(FilterValue::Bool(true));
+    }
+    if cur.rest().starts_with("false") {
+        cur.pos += 5;
+        return Ok(FilterValue::Bool(false));
+    }
+    if cur.rest().starts_with("null") {
+        cur.pos += 4;
+        return Ok(FilterValue::Null);
+    }
+
+    let start = cur.pos;
+    if cur.peek() == Some('-') {
+        cur.bump();
+    }
+    while matches!(cur.peek(), Some(c) if c.is_ascii_digit() || c == '.') {
+        cur.bump();
+    }
+    if cur.pos == start {
+        return Err(cur.err("expected a filter comparison value"));
+    }
+    cur.s[start..cur.pos]
+        .parse::<f64>()
+        .map(FilterValue::Number)
+        .map_err(|_| cur.err("invalid number in filter"))
+}
+
+fn apply_segment<'a>(current: &[&'a Value], segment: &Segment) -> Vec<&'a Value> {
+    match segment {
+        Segment::Select(selector) => {
+            let mut out = Vec::new();
+            for value in current {
+                apply_selector(value, selector, &mut out);
+            }
+            out
+        }
+        Segment::Descend(selector) => {
+            let mut nodes = Vec::new();
+            for value in current {
+                collect_descendants(value, &mut nodes);
+            }
+            let mut out = Vec::new();
+            for node in &nodes {
+                apply_selector(node, selector, &mut out);
+            }
+            out
+        }
+    }
+}
+
+fn collect_descendants<'a>(value: &'a Value, out: &mut Vec<&'a Value>) {
+    out.push(value);
+    match value {
+        Value::Object(map) => {
+            for v in map.values() {
+                collect_descendants(v, out);
+            }
+        }
+        Value::Array(arr) => {
+            for v in arr {
+                collect_descendants(v, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn apply_selector<'a>(value: &'a Value, selector: &Selector, out: &mut Vec<&'a Value>) {
+    match selector {
+        Selector::Name(name) => {
+            if let Value::Object(map) = value {
+                if let Some(v) = map.get(name) {
+                    out.push(v);
+                }
+            }
+        }
+        Selector::Wildcard => match value {
+            Value::Object(map) => out.extend(map.values()),
+            Value::Array(arr) => out.extend(arr.iter()),
+            _ => {}
+        },
+        Selector::Index(i) => {
+            if let Value::Array(arr) = value {
+                if let Some(idx) = resolve_index(*i, arr.len()) {
+                    out.push(&arr[idx]);
+                }
+            }
+        }
+        Selector::Slice { start, end, step } => {
+            if let Value::Array(arr) = value {
+                for idx in slice_indices(*start, *end, *step, arr.len()) {
+                    out.push(&arr[idx]);
+                }
+            }
+        }
+        Selector::Filter(expr) => {
+            if let Value::Array(arr) = value {
+                for item in arr {
+                    if filter_matches(item, expr) {
+                        out.push(item);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn resolve_index(i: i64, len: usize) -> Option<usize> {
+    let len = len as i64;
+    let idx = if i < 0 { len + i } else { i };
+    if idx >= 0 && idx < len { Some(idx as usize) } else { None }
+}
+
+fn slice_indices(start: Option<i64>, end: Option<i64>, step: i64, len: usize) -> Vec<usize> {
+    let len_i = len as i64;
+    let norm = |v: i64| -> i64 {
+        if v < 0 { (len_i + v).max(0) } else { v.min(len_i) }
+    };
+
+    let mut out = Vec::new();
+    if step > 0 {
+        let s = start.map(norm).unwrap_or(0);
+        let e = end.map(norm).unwrap_or(len_i);
+        let mut i = s;
+        while i < e {
+            if i >= 0 && i < len_i {
+                out.push(i as usize);
+            }
+            i += step;
+        }
+    } else {
+        let s = start.map(norm).unwrap_or(len_i - 1);
+        let e = end.map(norm).unwrap_or(-1);
+        let mut i = s;
+        while i > e {
+            if i >= 0 && i < len_i {
+                out.push(i as usize);
+            }
+            i += step;
+        }
+    }
+    out
+}
+
+fn filter_matches(item: &Value, expr: &FilterExpr) -> bool {
+    let field_value = match item {
+        Value::Object(map) => map.get(&expr.field),
+        _ => None,
+    };
+    match &expr.test {
+        FilterTest::Truthy => matches!(field_value, Some(v) if is_truthy(v)),
+        FilterTest::Compare(op, literal) => field_value.is_some_and(|v| compare(v, op, literal)),
+    }
+}
+
+fn is_truthy(v: &Value) -> bool {
+    !matches!(v, Value::Null | Value::Bool(false))
+}
+
+fn compare(v: &Value, op: &CompareOp, literal: &FilterValue) -> bool {
+    match (v, literal) {
+        (Value::Number(n), FilterValue::Number(lit)) => {
+            let Some(n) = n.as_f64() else { return false };
+            match op {
+                CompareOp::Eq => n == *lit,
+                CompareOp::Ne => n != *lit,
+                CompareOp::Lt => n < *lit,
+                CompareOp::Le => n <= *lit,
+                CompareOp::Gt => n > *lit,
+                CompareOp::Ge => n >= *lit,
+            }
+        }
+        (Value::String(s), FilterValue::String(lit)) => match op {
+            CompareOp::Eq => s == lit,
+            CompareOp::Ne => s != lit,
+            CompareOp::Lt => s.as_str() < lit.as_str(),
+            CompareOp::Le => s.as_str() <= lit.as_str(),
+            CompareOp::Gt => s.as_str() > lit.as_str(),
+            CompareOp::Ge => s.as_str() >= lit.as_str(),
+        },
+        (Value::Bool(b), FilterValue::Bool(lit)) => match op {
+            CompareOp::Eq => b == lit,
+            CompareOp::Ne => b != lit,
+            _ => false,
+        },
+        (Value::Null, FilterValue::Null) => matches!(op, CompareOp::Eq),
+        _ => matches!(op, CompareOp::Ne),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_select_root() {
+        let v = json!({"a": 1});
+        let out = select(&v, "$").unwrap();
+        assert_eq!(out, vec![&v]);
+    }
+
+    #[test]
+    fn test_select_child_name() {
+        let v = json!({"store": {"name": "acme"}});
+        let out = select(&v, "$.store.name").unwrap();
+        assert_eq!(out, vec![&json!("acme")]);
+    }
+
+    #[test]
+    fn test_select_bracket_name() {
+        let v = json!({"a b": 1});
+        let out = select(&v, "$['a b']").unwrap();
+        assert_eq!(out, vec![&json!(1)]);
+    }
+
+    #[test]
+    fn test_select_wildcard() {
+        let v = json!({"a": 1, "b": 2});
+        let out = select(&v, "$.*").unwrap();
+        assert_eq!(out.len(), 2);
+    }
+
+    #[test]
+    fn test_select_index_and_negative_index() {
+        let v = json!([10, 20, 30]);
+        assert_eq!(select(&v, "$[0]").unwrap(), vec![&json!(10)]);
+        assert_eq!(select(&v, "$[-1]").unwrap(), vec![&json!(30)]);
+    }
+
+    #[test]
+    fn test_select_slice() {
+        let v = json!([0, 1, 2, 3, 4]);
+        let out = select(&v, "$[1:3]").unwrap();
+        assert_eq!(out, vec![&json!(1), &json!(2)]);
+    }
+
+    #[test]
+    fn test_select_reverse_slice() {
+        let v = json!([0, 1, 2, 3, 4]);
+        let out = select(&v, "$[::-1]").unwrap();
+        assert_eq!(out, vec![&json!(4), &json!(3), &json!(2), &json!(1), &json!(0)]);
+    }
+
+    #[test]
+    fn test_select_recursive_descent() {
+        let v = json!({"store": {"book": [{"title": "a"}, {"nested": {"title": "b"}}]}});
+        let out = select(&v, "$..title").unwrap();
+        assert_eq!(out, vec![&json!("a"), &json!("b")]);
+    }
+
+    #[test]
+    fn test_select_filter() {
+        let v = json!({"book": [{"price": 8}, {"price": 15}, {"price": 22}]});
+        let out = select(&v, "$.book[?(@.price > 10)]").unwrap();
+        assert_eq!(out, vec![&json!({"price": 15}), &json!({"price": 22})]);
+    }
+
+    #[test]
+    fn test_select_filter_truthy() {
+        let v = json!({"book": [{"active": true}, {"active": false}, {}]});
+        let out = select(&v, "$.book[?(@.active)]").unwrap();
+        assert_eq!(out, vec![&json!({"active": true})]);
+    }
+
+    #[test]
+    fn test_select_invalid_expression() {
+        let v = json!({});
+        let err = select(&v, "store.book").unwrap_err();
+        assert_eq!(err.position, 0);
+    }
+
+    #[test]
+    fn test_project_single_match_is_unwrapped() {
+        let v = json!({"store": {"title": "acme"}});
+        let opts = ToonOptions { select: Some("$.store.title".to_string()), ..ToonOptions::default() };
+        let projected = project(&v, &opts).unwrap();
+        assert_eq!(*projected, json!("acme"));
+    }
+
+    #[test]
+    fn test_project_multiple_matches_wrap_into_array() {
+        let v = json!({"book": [{"title": "a"}, {"title": "b"}]});
+        let opts = ToonOptions { select: Some("$.book[*].title".to_string()), ..ToonOptions::default() };
+        let projected = project(&v, &opts).unwrap();
+        assert_eq!(*projected, json!(["a", "b"]));
+    }
+
+    #[test]
+    fn test_project_without_select_borrows() {
+        let v = json!({"a": 1});
+        let opts = ToonOptions::default();
+        let projected = project(&v, &opts).unwrap();
+        assert!(matches!(projected, Cow::Borrowed(_)));
+    }
+}
@@ -1,7 +1,8 @@
-//! TOON (Token-Oriented Object Notation) encoder.
+//! TOON (Token-Oriented Object Notation) encoder/decoder.
 //!
-//! Converts [`serde_json::Value`] to TOON — a compact, human-readable format
-//! that reduces token usage by 30–60% compared to JSON.
+//! Converts [`serde_json::Value`] to and from TOON — a compact,
+//! human-readable format that reduces token usage by 30–60% compared to
+//! JSON.
 //!
 //! # Example
 //!
@@ -14,8 +15,18 @@
 //! assert_eq!(output, "name: Alice\nage: 30");
 //! ```
 
+use std::io;
+
 use serde_json::Value;
 
+mod decoder;
+mod jsonpath;
+mod ser;
+
+pub use decoder::{DecodeError, decode_toon};
+pub use jsonpath::{JsonPathError, project, select};
+pub use ser::{SerializeError, Serializer, to_string, to_writer};
+
 /// Delimiter used between array elements and tabular row values.
 #[derive(Debug, Clone, Copy, Default)]
 pub enum Delimiter {
@@ -34,6 +45,7 @@ pub enum KeyFolding {
 }
 
 /// Options controlling TOON encoding.
+#[derive(Debug, Clone)]
 pub struct ToonOptions {
     /// Delimiter between array/row values.
     pub delimiter: Delimiter,
@@ -43,6 +55,16 @@ pub struct ToonOptions {
     pub key_folding: KeyFolding,
     /// Maximum number of levels to fold (default: [`usize::MAX`]).
     pub flatten_depth: usize,
+    /// JSONPath expression selecting the subtree to encode (default: the
+    /// whole document). See [`project`] for how it's applied.
+    pub select: Option<String>,
+    /// Pass numbers through using their exact original text instead of
+    /// routing them through `f64`. Only takes effect for numbers that don't
+    /// already fit in an `i64`/`u64` (those are always emitted exactly);
+    /// meaningful mainly when `value` was parsed with serde_json's
+    /// `arbitrary_precision` feature, since that's what lets a `Number`
+    /// remember its original token.
+    pub raw_numbers: bool,
 }
 
 impl Default for ToonOptions {
@@ -52,22 +74,36 @@ impl Default for ToonOptions {
             indent: 2,
             key_folding: KeyFolding::Off,
             flatten_depth: usize::MAX,
+            select: None,
+            raw_numbers: false,
         }
     }
 }
 
 /// Encode a JSON value as TOON.
+///
+/// Layered on top of [`to_string`]: a [`Value`] already implements
+/// [`serde::Serialize`], so this simply drives the generic serializer.
+/// Serializing a `Value` can never fail (it holds no type that our
+/// [`Serializer`] rejects), so the error case is unreachable.
 pub fn encode_toon(value: &Value, opts: &ToonOptions) -> String {
-    let mut out = String::new();
-    encode_value(&mut out, value, 0, opts, true);
-    while out.ends_with('\n') {
-        out.pop();
+    to_string(value, opts).expect("serializing a `serde_json::Value` is infallible")
+}
+
+/// Encode a JSON value as TOON directly into `w`, without building the
+/// whole document in memory first.
+pub fn encode_toon_to_writer<W: io::Write>(value: &Value, opts: &ToonOptions, w: &mut W) -> io::Result<()> {
+    match to_writer(&mut *w, value, opts) {
+        Ok(()) => Ok(()),
+        Err(SerializeError::Io(e)) => Err(e),
+        Err(SerializeError::Message(msg)) => {
+            unreachable!("serializing a `serde_json::Value` is infallible: {msg}")
+        }
     }
-    out
 }
 
 impl Delimiter {
-    fn char(self) -> char {
+    pub(crate) fn char(self) -> char {
         match self {
             Self::Comma => ',',
             Self::Tab => '\t',
@@ -75,7 +111,7 @@ impl Delimiter {
         }
     }
 
-    fn header_symbol(self) -> &'static str {
+    pub(crate) fn header_symbol(self) -> &'static str {
         match self {
             Self::Comma => "",
             Self::Tab => "\t",
@@ -84,241 +120,7 @@ impl Delimiter {
     }
 }
 
-fn encode_value(out: &mut String, value: &Value, depth: usize, opts: &ToonOptions, is_root: bool) {
-    match value {
-        Value::Object(map) => encode_object(out, map, depth, opts, is_root),
-        Value::Array(arr) => encode_array_field(out, "", arr, depth, opts, ""),
-        _ if is_root => out.push_str(&format_scalar(value, opts.delimiter)),
-        _ => {}
-    }
-}
-
-fn encode_object(
-    out: &mut String,
-    map: &serde_json::Map<String, Value>,
-    depth: usize,
-    opts: &ToonOptions,
-    _is_root: bool,
-) {
-    let indent = " ".repeat(depth * opts.indent);
-    let mut first = true;
-
-    for (key, value) in map {
-        if !first {
-            out.push('\n');
-        }
-
-        if matches!(opts.key_folding, KeyFolding::Safe) && is_valid_identifier(key) {
-            let mut chain = vec![key.as_str()];
-            let mut current = value;
-            while chain.len() - 1 < opts.flatten_depth {
-                if let Value::Object(inner) = current {
-                    if inner.len() == 1 {
-                        let (k, v) = inner.iter().next().unwrap();
-                        if is_valid_identifier(k) && !needs_quoting(k, opts.delimiter) {
-                            chain.push(k.as_str());
-                            current = v;
-                            continue;
-                        }
-                    }
-                }
-                break;
-            }
-
-            if chain.len() > 1 {
-                let folded_key = chain.join(".");
-                encode_field(out, &folded_key, current, depth, opts, &indent);
-                first = false;
-                continue;
-            }
-        }
-
-        encode_field(out, key, value, depth, opts, &indent);
-        first = false;
-    }
-}
-
-fn encode_field(
-    out: &mut String,
-    key: &str,
-    value: &Value,
-    depth: usize,
-    opts: &ToonOptions,
-    indent: &str,
-) {
-    let fkey = format_key(key, opts.delimiter);
-
-    match value {
-        Value::Object(inner) if !inner.is_empty() => {
-            out.push_str(indent);
-            out.push_str(&fkey);
-            out.push(':');
-            out.push('\n');
-            encode_object(out, inner, depth + 1, opts, false);
-        }
-        Value::Object(_) => {
-            out.push_str(indent);
-            out.push_str(&fkey);
-            out.push(':');
-        }
-        Value::Array(arr) => {
-            encode_array_field(out, &fkey, arr, depth, opts, indent);
-        }
-        _ => {
-            out.push_str(indent);
-            out.push_str(&fkey);
-            out.push_str(": ");
-            out.push_str(&format_scalar(value, opts.delimiter));
-        }
-    }
-}
-
-fn encode_array_field(
-    out: &mut String,
-    key: &str,
-    arr: &[Value],
-    depth: usize,
-    opts: &ToonOptions,
-    indent: &str,
-) {
-    let n = arr.len();
-    let dsym = opts.delimiter.header_symbol();
-    let delim_ch = opts.delimiter.char();
-
-    if n == 0 {
-        out.push_str(indent);
-        out.push_str(key);
-        out.push_str(&format!("[0{dsym}]:"));
-        return;
-    }
-
-    // All primitives -> inline
-    if arr.iter().all(is_primitive) {
-        let values: Vec<String> = arr
-            .iter()
-            .map(|v| format_scalar(v, opts.delimiter))
-            .collect();
-        out.push_str(indent);
-        out.push_str(key);
-        out.push_str(&format!("[{n}{dsym}]: "));
-        out.push_str(&values.join(&delim_ch.to_string()));
-        return;
-    }
-
-    // Tabular: all objects with identical keys, all primitive values
-    if let Some(fields) = detect_tabular(arr) {
-        let field_names: Vec<String> = fields
-            .iter()
-            .map(|f| format_key(f, opts.delimiter))
-            .collect();
-        let field_header = field_names.join(&delim_ch.to_string());
-        out.push_str(indent);
-        out.push_str(key);
-        out.push_str(&format!("[{n}{dsym}]{{{field_header}}}:"));
-
-        let child_indent = " ".repeat((depth + 1) * opts.indent);
-        for item in arr {
-            if let Value::Object(map) = item {
-                let row: Vec<String> = fields
-                    .iter()
-                    .map(|f| format_scalar(map.get(f).unwrap_or(&Value::Null), opts.delimiter))
-                    .collect();
-                out.push('\n');
-                out.push_str(&child_indent);
-                out.push_str(&row.join(&delim_ch.to_string()));
-            }
-        }
-        return;
-    }
-
-    // Expanded list form
-    out.push_str(indent);
-    out.push_str(key);
-    out.push_str(&format!("[{n}{dsym}]:"));
-
-    let child_indent = " ".repeat((depth + 1) * opts.indent);
-    for item in arr {
-        out.push('\n');
-        match item {
-            Value::Object(map) if !map.is_empty() => {
-                let mut obj_out = String::new();
-                encode_object(&mut obj_out, map, depth + 2, opts, false);
-                // First field goes on same line as `-`
-                if let Some(first_newline) = obj_out.find('\n') {
-                    let first_line = &obj_out[..first_newline];
-                    let rest = &obj_out[first_newline..];
-                    out.push_str(&child_indent);
-                    out.push_str("- ");
-                    out.push_str(first_line.trim_start());
-                    out.push_str(rest);
-                } else {
-                    out.push_str(&child_indent);
-                    out.push_str("- ");
-                    out.push_str(obj_out.trim_start());
-                }
-            }
-            Value::Object(_) => {
-                out.push_str(&child_indent);
-                out.push('-');
-            }
-            Value::Array(inner) => {
-                let inner_n = inner.len();
-                out.push_str(&child_indent);
-                if inner.iter().all(is_primitive) {
-                    let values: Vec<String> = inner
-                        .iter()
-                        .map(|v| format_scalar(v, opts.delimiter))
-                        .collect();
-                    out.push_str(&format!("- [{inner_n}{dsym}]: "));
-                    out.push_str(&values.join(&delim_ch.to_string()));
-                } else {
-                    out.push_str(&format!("- [{inner_n}{dsym}]:"));
-                    let nested_indent = " ".repeat((depth + 2) * opts.indent);
-                    for inner_item in inner {
-                        out.push('\n');
-                        out.push_str(&nested_indent);
-                        out.push_str("- ");
-                        out.push_str(&format_scalar(inner_item, opts.delimiter));
-                    }
-                }
-            }
-            _ => {
-                out.push_str(&child_indent);
-                out.push_str("- ");
-                out.push_str(&format_scalar(item, opts.delimiter));
-            }
-        }
-    }
-}
-
-fn detect_tabular(arr: &[Value]) -> Option<Vec<String>> {
-    let first = arr.first()?.as_object()?;
-    let keys: Vec<String> = first.keys().cloned().collect();
-    if keys.is_empty() || !first.values().all(is_primitive) {
-        return None;
-    }
-    for item in &arr[1..] {
-        let obj = item.as_object()?;
-        if obj.len() != keys.len() {
-            return None;
-        }
-        for key in &keys {
-            if !is_primitive(obj.get(key)?) {
-                return None;
-            }
-        }
-    }
-    Some(keys)
-}
-
-fn is_primitive(v: &Value) -> bool {
-    matches!(
-        v,
-        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_)
-    )
-}
-
-fn is_valid_identifier(s: &str) -> bool {
+pub(crate) fn is_valid_identifier(s: &str) -> bool {
     let mut chars = s.chars();
     match chars.next() {
         Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
@@ -327,7 +129,7 @@ fn is_valid_identifier(s: &str) -> bool {
     chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
-fn needs_quoting(s: &str, delimiter: Delimiter) -> bool {
+pub(crate) fn needs_quoting(s: &str, delimiter: Delimiter) -> bool {
     if s.is_empty() || matches!(s, "true" | "false" | "null") || s.starts_with('-') {
         return true;
     }
@@ -349,7 +151,7 @@ fn needs_quoting(s: &str, delimiter: Delimiter) -> bool {
     })
 }
 
-fn looks_like_number(s: &str) -> bool {
+pub(crate) fn looks_like_number(s: &str) -> bool {
     let b = s.as_bytes();
     let mut i = 0;
     if i < b.len() && b[i] == b'-' {
@@ -385,7 +187,7 @@ fn looks_like_number(s: &str) -> bool {
     i == b.len()
 }
 
-fn format_key(key: &str, delimiter: Delimiter) -> String {
+pub(crate) fn format_key(key: &str, delimiter: Delimiter) -> String {
     if needs_quoting(key, delimiter) {
         format!("\"{}\"", escape_string(key))
     } else {
@@ -393,48 +195,38 @@ fn format_key(key: &str, delimiter: Delimiter) -> String {
     }
 }
 
-fn format_scalar(value: &Value, delimiter: Delimiter) -> String {
-    match value {
-        Value::Null => "null".to_string(),
-        Value::Bool(b) => b.to_string(),
-        Value::Number(n) => format_number(n),
-        Value::String(s) => {
-            if needs_quoting(s, delimiter) {
-                format!("\"{}\"", escape_string(s))
-            } else {
-                s.clone()
-            }
-        }
-        _ => serde_json::to_string(value).unwrap_or_default(),
+pub(crate) fn format_number(n: &serde_json::Number, raw_numbers: bool) -> String {
+    // Integers that fit in i64/u64 carry an exact representation — use it
+    // directly rather than routing through `f64`, which silently corrupts
+    // values beyond 2^53.
+    if let Some(i) = n.as_i64() {
+        return i.to_string();
+    }
+    if let Some(u) = n.as_u64() {
+        return u.to_string();
     }
-}
 
-fn format_number(n: &serde_json::Number) -> String {
-    if let Some(f) = n.as_f64() {
-        if f == 0.0 {
-            return "0".to_string();
-        }
-        if f.is_nan() || f.is_infinite() {
-            return "null".to_string();
-        }
-        if f.fract() == 0.0 && f.abs() < (i64::MAX as f64) {
-            return format!("{}", f as i64);
-        }
-        let s = format!("{f}");
-        if s.contains('e') || s.contains('E') {
-            let formatted = format!("{f:.20}");
-            return formatted
-                .trim_end_matches('0')
-                .trim_end_matches('.')
-                .to_string();
-        }
-        s
-    } else {
-        n.to_string()
+    // Past this point the number is genuinely fractional, or an integer too
+    // large for i64/u64 (only reachable with serde_json's
+    // `arbitrary_precision` feature). With `raw_numbers` on, `Number`
+    // remembers its original token under that feature, so emit it verbatim
+    // instead of rounding it through `f64`.
+    if raw_numbers {
+        return n.to_string();
     }
+
+    let f = n.as_f64().unwrap_or(0.0);
+    if f == 0.0 {
+        return "0".to_string();
+    }
+    if f.is_nan() || f.is_infinite() {
+        return "null".to_string();
+    }
+    // `{}` on f64 already picks the shortest representation that round-trips.
+    format!("{f}")
 }
 
-fn escape_string(s: &str) -> String {
+pub(crate) fn escape_string(s: &str) -> String {
     let mut out = String::with_capacity(s.len());
     for c in s.chars() {
         match c {
@@ -545,6 +337,22 @@ mod tests {
         assert_eq!(out, "a: 1\nb: 0\nc: 3.14");
     }
 
+    #[test]
+    fn test_large_integer_survives_without_precision_loss() {
+        // 2^53 + 1: the smallest integer that `f64` can no longer represent
+        // exactly, so routing it through `as_f64()` would corrupt it.
+        let v: Value = serde_json::from_str(r#"{"id":9007199254740993}"#).unwrap();
+        let out = encode_toon(&v, &default_opts());
+        assert_eq!(out, "id: 9007199254740993");
+    }
+
+    #[test]
+    fn test_negative_large_integer_survives_without_precision_loss() {
+        let v: Value = serde_json::from_str(r#"{"id":-9007199254740993}"#).unwrap();
+        let out = encode_toon(&v, &default_opts());
+        assert_eq!(out, "id: -9007199254740993");
+    }
+
     #[test]
     fn test_flatten_depth() {
         let v: Value = serde_json::from_str(r#"{"a":{"b":{"c":{"d":"val"}}}}"#).unwrap();
@@ -556,4 +364,13 @@ mod tests {
         let out = encode_toon(&v, &opts);
         assert_eq!(out, "a.b:\n  c.d: val");
     }
+
+    #[test]
+    fn test_encode_toon_to_writer_matches_encode_toon() {
+        let v: Value = serde_json::from_str(r#"{"users":[{"id":1,"name":"Alice"},{"id":2,"name":"Bob"}]}"#).unwrap();
+        let opts = default_opts();
+        let mut buf = Vec::new();
+        encode_toon_to_writer(&v, &opts, &mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), encode_toon(&v, &opts));
+    }
 }
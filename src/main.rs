@@ -1,8 +1,8 @@
-use std::io::{self, Read};
+use std::io::{self, BufWriter, Read, Write};
 use std::path::PathBuf;
 
 use clap::{Parser, ValueEnum};
-use format_as_toon::{Delimiter, KeyFolding, ToonOptions, encode_toon};
+use format_as_toon::{Delimiter, KeyFolding, ToonOptions, encode_toon_to_writer, project};
 use serde_json::Value;
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -42,6 +42,16 @@ struct Args {
     /// Maximum depth for key folding (default: unlimited)
     #[arg(short, long)]
     flatten_depth: Option<usize>,
+
+    /// JSONPath expression selecting the subtree to encode, e.g. `$.store.book[*].title`
+    #[arg(short = 'S', long)]
+    select: Option<String>,
+
+    /// Preserve integers beyond u64 range and high-precision decimals
+    /// exactly, instead of rounding them through f64 (only when built with
+    /// the `arbitrary_precision` feature)
+    #[arg(long)]
+    raw_numbers: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -70,10 +80,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             KeyFoldingArg::Safe => KeyFolding::Safe,
         },
         flatten_depth: args.flatten_depth.unwrap_or(usize::MAX),
+        select: args.select,
+        raw_numbers: args.raw_numbers,
     };
 
-    let output = encode_toon(&value, &opts);
-    print!("{output}");
+    let projected = project(&value, &opts)?;
+
+    let mut out = BufWriter::new(io::stdout());
+    encode_toon_to_writer(&projected, &opts, &mut out)?;
+    out.flush()?;
 
     Ok(())
 }
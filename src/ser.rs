@@ -0,0 +1,844 @@
+//! A [`serde::Serializer`] that encodes any [`Serialize`] type straight to
+//! TOON, without materializing a [`serde_json::Value`] first.
+//!
+//! TOON's tabular/inline array forms need to look ahead at all of a
+//! sequence's elements before choosing how to render it, so `serialize_seq`
+//! and `serialize_map` can't stream field-by-field like a text format
+//! normally would. Instead they buffer their children into [`Node`], a
+//! small tree mirroring [`serde_json::Value`]'s shape, and the existing
+//! tabular-detection/encoding logic runs over that buffer once it's
+//! complete.
+//!
+//! `NodeBuilder` also unwraps `serde_json::Number`'s `arbitrary_precision`
+//! sentinel struct back into [`Node::Number`] (see `StructBuilder`), so it
+//! round-trips correctly when the final binary enables our own
+//! `arbitrary_precision` Cargo feature (which must forward to
+//! `serde_json/arbitrary_precision`) — otherwise every number, not just
+//! oversized ones, would be buffered as a bogus one-field object.
+
+use std::fmt;
+use std::io;
+
+use serde::ser::{
+    Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+    SerializeTupleStruct, SerializeTupleVariant,
+};
+use serde_json::Number;
+
+use crate::{KeyFolding, ToonOptions, escape_string, format_key, format_number, is_valid_identifier, needs_quoting};
+
+/// Error produced while serializing a value to TOON.
+#[derive(Debug)]
+pub enum SerializeError {
+    /// A value can't be represented in TOON (e.g. a non-string map key, or
+    /// a non-finite float).
+    Message(String),
+    /// Writing the encoded output failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Message(msg) => f.write_str(msg),
+            Self::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for SerializeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Message(_) => None,
+        }
+    }
+}
+
+impl serde::ser::Error for SerializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+impl From<io::Error> for SerializeError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Encode `value` to a TOON string, driving the encoding directly from its
+/// [`Serialize`] implementation.
+pub fn to_string<T: Serialize + ?Sized>(value: &T, opts: &ToonOptions) -> Result<String, SerializeError> {
+    let node = value.serialize(NodeBuilder)?;
+    Ok(encode_node_to_string(&node, opts))
+}
+
+/// Encode `value` to TOON and write it to `writer`.
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(writer: W, value: &T, opts: &ToonOptions) -> Result<(), SerializeError> {
+    Serializer::new(writer, opts.clone()).serialize(value)
+}
+
+/// Drives TOON encoding of a [`Serialize`] value into a [`io::Write`] sink.
+pub struct Serializer<W: io::Write> {
+    writer: W,
+    opts: ToonOptions,
+}
+
+impl<W: io::Write> Serializer<W> {
+    pub fn new(writer: W, opts: ToonOptions) -> Self {
+        Self { writer, opts }
+    }
+
+    /// Serialize `value` and write the resulting TOON document.
+    pub fn serialize<T: Serialize + ?Sized>(mut self, value: &T) -> Result<(), SerializeError> {
+        let node = value.serialize(NodeBuilder)?;
+        encode_node_to_writer(&node, &self.opts, &mut self.writer)?;
+        Ok(())
+    }
+
+    /// Consume the serializer, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Intermediate tree buffered by [`NodeBuilder`] — a `serde`-agnostic
+/// mirror of [`serde_json::Value`] that the encoder walks. A `Value` is
+/// itself encoded by first buffering it into this tree.
+pub(crate) enum Node {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+fn node_to_key(node: Node) -> Result<String, SerializeError> {
+    match node {
+        Node::String(s) => Ok(s),
+        Node::Number(n) => Ok(n.to_string()),
+        Node::Bool(b) => Ok(b.to_string()),
+        _ => Err(SerializeError::Message("map keys must serialize to a string, number, or bool".to_string())),
+    }
+}
+
+/// A [`serde::Serializer`] whose `Ok` type is [`Node`] rather than text —
+/// serializing builds the buffer; encoding to TOON happens afterward.
+struct NodeBuilder;
+
+pub(crate) struct SeqBuilder {
+    items: Vec<Node>,
+}
+
+pub(crate) struct TupleVariantBuilder {
+    name: &'static str,
+    items: Vec<Node>,
+}
+
+pub(crate) struct MapBuilder {
+    entries: Vec<(String, Node)>,
+    pending_key: Option<String>,
+}
+
+pub(crate) struct StructVariantBuilder {
+    name: &'static str,
+    entries: Vec<(String, Node)>,
+}
+
+/// The struct/field name `serde_json::Number`'s `Serialize` impl routes
+/// through when its `arbitrary_precision` feature is enabled — it isn't a
+/// public constant, so we duplicate the literal the same way other
+/// third-party `Serializer` impls (`toml`, `ron`, …) do.
+const ARBITRARY_PRECISION_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// [`NodeBuilder::serialize_struct`]'s result type. Ordinarily a struct
+/// becomes a [`Node::Object`] via [`MapBuilder`], but `serde_json::Number`
+/// under `arbitrary_precision` instead serializes itself as a one-field
+/// struct tagged [`ARBITRARY_PRECISION_NUMBER_TOKEN`] carrying its raw
+/// numeric text — without this, that sentinel struct would be buffered
+/// as a literal object field instead of unwrapped back into a number.
+pub(crate) enum StructBuilder {
+    Map(MapBuilder),
+    Number(Option<String>),
+}
+
+impl serde::Serializer for NodeBuilder {
+    type Ok = Node;
+    type Error = SerializeError;
+    type SerializeSeq = SeqBuilder;
+    type SerializeTuple = SeqBuilder;
+    type SerializeTupleStruct = SeqBuilder;
+    type SerializeTupleVariant = TupleVariantBuilder;
+    type SerializeMap = MapBuilder;
+    type SerializeStruct = StructBuilder;
+    type SerializeStructVariant = StructVariantBuilder;
+
+    fn serialize_bool(self, v: bool) -> Result<Node, SerializeError> {
+        Ok(Node::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Node, SerializeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Node, SerializeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Node, SerializeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Node, SerializeError> {
+        Ok(Node::Number(v.into()))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Node, SerializeError> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) => Err(SerializeError::Message(format!("{v} does not fit in an i64"))),
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Node, SerializeError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Node, SerializeError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Node, SerializeError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Node, SerializeError> {
+        Ok(Node::Number(v.into()))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Node, SerializeError> {
+        match u64::try_from(v) {
+            Ok(v) => self.serialize_u64(v),
+            Err(_) => Err(SerializeError::Message(format!("{v} does not fit in a u64"))),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Node, SerializeError> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Node, SerializeError> {
+        Number::from_f64(v)
+            .map(Node::Number)
+            .ok_or_else(|| SerializeError::Message("non-finite float cannot be represented in TOON".to_string()))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Node, SerializeError> {
+        Ok(Node::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Node, SerializeError> {
+        Ok(Node::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Node, SerializeError> {
+        Ok(Node::Array(v.iter().map(|b| Node::Number((*b).into())).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Node, SerializeError> {
+        Ok(Node::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Node, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Node, SerializeError> {
+        Ok(Node::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Node, SerializeError> {
+        Ok(Node::Null)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Node, SerializeError> {
+        Ok(Node::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<Node, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node, SerializeError> {
+        Ok(Node::Object(vec![(variant.to_string(), value.serialize(NodeBuilder)?)]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqBuilder, SerializeError> {
+        Ok(SeqBuilder { items: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqBuilder, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqBuilder, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<TupleVariantBuilder, SerializeError> {
+        Ok(TupleVariantBuilder { name: variant, items: Vec::with_capacity(len) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapBuilder, SerializeError> {
+        Ok(MapBuilder { entries: Vec::new(), pending_key: None })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<StructBuilder, SerializeError> {
+        if name == ARBITRARY_PRECISION_NUMBER_TOKEN {
+            Ok(StructBuilder::Number(None))
+        } else {
+            Ok(StructBuilder::Map(MapBuilder { entries: Vec::with_capacity(len), pending_key: None }))
+        }
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<StructVariantBuilder, SerializeError> {
+        Ok(StructVariantBuilder { name: variant, entries: Vec::with_capacity(len) })
+    }
+}
+
+impl SerializeSeq for SeqBuilder {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.items.push(value.serialize(NodeBuilder)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        Ok(Node::Array(self.items))
+    }
+}
+
+impl SerializeTuple for SeqBuilder {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeTupleStruct for SeqBuilder {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeTupleVariant for TupleVariantBuilder {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.items.push(value.serialize(NodeBuilder)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        Ok(Node::Object(vec![(self.name.to_string(), Node::Array(self.items))]))
+    }
+}
+
+impl SerializeMap for MapBuilder {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.pending_key = Some(node_to_key(key.serialize(NodeBuilder)?)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let key = self
+            .pending_key
+            .take()
+            .ok_or_else(|| SerializeError::Message("serialize_value called before serialize_key".to_string()))?;
+        self.entries.push((key, value.serialize(NodeBuilder)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        Ok(Node::Object(self.entries))
+    }
+}
+
+impl SerializeStruct for MapBuilder {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerializeError> {
+        self.entries.push((key.to_string(), value.serialize(NodeBuilder)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        Ok(Node::Object(self.entries))
+    }
+}
+
+impl SerializeStruct for StructBuilder {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerializeError> {
+        match self {
+            Self::Map(map) => map.serialize_field(key, value),
+            Self::Number(raw) => {
+                let Node::String(text) = value.serialize(NodeBuilder)? else {
+                    return Err(SerializeError::Message(
+                        "expected serde_json's arbitrary-precision number field to serialize as a string".to_string(),
+                    ));
+                };
+                *raw = Some(text);
+                Ok(())
+            }
+        }
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        match self {
+            // `MapBuilder` implements both `SerializeMap::end` and
+            // `SerializeStruct::end` with identical signatures, so the call
+            // must be disambiguated explicitly.
+            Self::Map(map) => SerializeStruct::end(map),
+            Self::Number(raw) => {
+                let text = raw.ok_or_else(|| {
+                    SerializeError::Message("serde_json's arbitrary-precision number had no field".to_string())
+                })?;
+                arbitrary_precision_number_from_str(&text)
+            }
+        }
+    }
+}
+
+/// Parses the raw numeric text captured from serde_json's
+/// `arbitrary_precision` sentinel struct back into a [`Node::Number`].
+///
+/// `serde_json::Number` only implements [`std::str::FromStr`] when its own
+/// `arbitrary_precision` feature is enabled — which is exactly the
+/// condition under which this function is ever reached, since that's the
+/// only configuration where `ARBITRARY_PRECISION_NUMBER_TOKEN` appears.
+#[cfg(feature = "arbitrary_precision")]
+fn arbitrary_precision_number_from_str(text: &str) -> Result<Node, SerializeError> {
+    text.parse::<Number>()
+        .map(Node::Number)
+        .map_err(|e| SerializeError::Message(format!("invalid arbitrary-precision number token {text:?}: {e}")))
+}
+
+#[cfg(not(feature = "arbitrary_precision"))]
+fn arbitrary_precision_number_from_str(_text: &str) -> Result<Node, SerializeError> {
+    unreachable!(
+        "serde_json only emits the arbitrary-precision number sentinel when its own \
+         `arbitrary_precision` feature is enabled, which requires ours to be too"
+    )
+}
+
+impl SerializeStructVariant for StructVariantBuilder {
+    type Ok = Node;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerializeError> {
+        self.entries.push((key.to_string(), value.serialize(NodeBuilder)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, SerializeError> {
+        Ok(Node::Object(vec![(self.name.to_string(), Node::Object(self.entries))]))
+    }
+}
+
+// --- Encoding: walks the buffered `Node` tree and writes TOON text.
+
+/// Wraps a writer with a deferred-newline flag: the separator between two
+/// lines is never written until the line after it actually produces output,
+/// so a document that would otherwise end in `\n` just never gets one —
+/// no `String::pop()`-style post-hoc trim needed.
+struct Sink<'a, W: io::Write> {
+    writer: &'a mut W,
+    pending_newline: bool,
+}
+
+impl<'a, W: io::Write> Sink<'a, W> {
+    fn new(writer: &'a mut W) -> Self {
+        Self { writer, pending_newline: false }
+    }
+
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        if s.is_empty() {
+            return Ok(());
+        }
+        self.flush_pending()?;
+        self.writer.write_all(s.as_bytes())
+    }
+
+    fn newline(&mut self) {
+        self.pending_newline = true;
+    }
+
+    fn flush_pending(&mut self) -> io::Result<()> {
+        if self.pending_newline {
+            self.pending_newline = false;
+            self.writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn encode_node_to_string(node: &Node, opts: &ToonOptions) -> String {
+    let mut buf = Vec::new();
+    encode_node_to_writer(node, opts, &mut buf).expect("writing to a Vec<u8> cannot fail");
+    String::from_utf8(buf).expect("encoder only ever writes valid UTF-8")
+}
+
+pub(crate) fn encode_node_to_writer<W: io::Write>(node: &Node, opts: &ToonOptions, writer: &mut W) -> io::Result<()> {
+    let mut sink = Sink::new(writer);
+    match node {
+        Node::Object(fields) => encode_object(&mut sink, fields, 0, opts, false)?,
+        Node::Array(arr) => encode_array_field(&mut sink, "", arr, 0, opts, "")?,
+        scalar => sink.write_str(&format_scalar(scalar, opts))?,
+    }
+    Ok(())
+}
+
+/// Encodes `fields` as an object body. When `skip_first_indent` is set (used
+/// when a containing array item already wrote `"- "` in the indent's place),
+/// the first field's own indent is omitted.
+fn encode_object<W: io::Write>(
+    sink: &mut Sink<W>,
+    fields: &[(String, Node)],
+    depth: usize,
+    opts: &ToonOptions,
+    skip_first_indent: bool,
+) -> io::Result<()> {
+    let indent = " ".repeat(depth * opts.indent);
+    let mut first = true;
+
+    for (key, value) in fields {
+        if !first {
+            sink.newline();
+        }
+        let indent = if first && skip_first_indent { "" } else { &indent };
+
+        if matches!(opts.key_folding, KeyFolding::Safe) && is_valid_identifier(key) {
+            let mut chain = vec![key.as_str()];
+            let mut current = value;
+            while chain.len() - 1 < opts.flatten_depth {
+                if let Node::Object(inner) = current {
+                    if inner.len() == 1 {
+                        let (k, v) = &inner[0];
+                        if is_valid_identifier(k) && !needs_quoting(k, opts.delimiter) {
+                            chain.push(k.as_str());
+                            current = v;
+                            continue;
+                        }
+                    }
+                }
+                break;
+            }
+
+            if chain.len() > 1 {
+                let folded_key = chain.join(".");
+                encode_field(sink, &folded_key, current, depth, opts, indent)?;
+                first = false;
+                continue;
+            }
+        }
+
+        encode_field(sink, key, value, depth, opts, indent)?;
+        first = false;
+    }
+    Ok(())
+}
+
+fn encode_field<W: io::Write>(sink: &mut Sink<W>, key: &str, value: &Node, depth: usize, opts: &ToonOptions, indent: &str) -> io::Result<()> {
+    let fkey = format_key(key, opts.delimiter);
+
+    match value {
+        Node::Object(inner) if !inner.is_empty() => {
+            sink.write_str(indent)?;
+            sink.write_str(&fkey)?;
+            sink.write_str(":")?;
+            sink.newline();
+            encode_object(sink, inner, depth + 1, opts, false)?;
+        }
+        Node::Object(_) => {
+            sink.write_str(indent)?;
+            sink.write_str(&fkey)?;
+            sink.write_str(":")?;
+        }
+        Node::Array(arr) => {
+            encode_array_field(sink, &fkey, arr, depth, opts, indent)?;
+        }
+        _ => {
+            sink.write_str(indent)?;
+            sink.write_str(&fkey)?;
+            sink.write_str(": ")?;
+            sink.write_str(&format_scalar(value, opts))?;
+        }
+    }
+    Ok(())
+}
+
+fn encode_array_field<W: io::Write>(
+    sink: &mut Sink<W>,
+    key: &str,
+    arr: &[Node],
+    depth: usize,
+    opts: &ToonOptions,
+    indent: &str,
+) -> io::Result<()> {
+    let n = arr.len();
+    let dsym = opts.delimiter.header_symbol();
+    let delim_ch = opts.delimiter.char();
+
+    if n == 0 {
+        sink.write_str(indent)?;
+        sink.write_str(key)?;
+        sink.write_str(&format!("[0{dsym}]:"))?;
+        return Ok(());
+    }
+
+    if arr.iter().all(is_primitive) {
+        let values: Vec<String> = arr.iter().map(|v| format_scalar(v, opts)).collect();
+        sink.write_str(indent)?;
+        sink.write_str(key)?;
+        sink.write_str(&format!("[{n}{dsym}]: "))?;
+        sink.write_str(&values.join(&delim_ch.to_string()))?;
+        return Ok(());
+    }
+
+    if let Some(fields) = detect_tabular(arr) {
+        let field_names: Vec<String> = fields.iter().map(|f| format_key(f, opts.delimiter)).collect();
+        let field_header = field_names.join(&delim_ch.to_string());
+        sink.write_str(indent)?;
+        sink.write_str(key)?;
+        sink.write_str(&format!("[{n}{dsym}]{{{field_header}}}:"))?;
+
+        let child_indent = " ".repeat((depth + 1) * opts.indent);
+        for item in arr {
+            if let Node::Object(obj) = item {
+                let row: Vec<String> = fields
+                    .iter()
+                    .map(|f| format_scalar(node_get(obj, f).unwrap_or(&Node::Null), opts))
+                    .collect();
+                sink.newline();
+                sink.write_str(&child_indent)?;
+                sink.write_str(&row.join(&delim_ch.to_string()))?;
+            }
+        }
+        return Ok(());
+    }
+
+    sink.write_str(indent)?;
+    sink.write_str(key)?;
+    sink.write_str(&format!("[{n}{dsym}]:"))?;
+
+    let child_indent = " ".repeat((depth + 1) * opts.indent);
+    for item in arr {
+        sink.newline();
+        match item {
+            Node::Object(obj) if !obj.is_empty() => {
+                sink.write_str(&child_indent)?;
+                sink.write_str("- ")?;
+                encode_object(sink, obj, depth + 2, opts, true)?;
+            }
+            Node::Object(_) => {
+                sink.write_str(&child_indent)?;
+                sink.write_str("-")?;
+            }
+            Node::Array(inner) => {
+                let inner_n = inner.len();
+                sink.write_str(&child_indent)?;
+                if inner.iter().all(is_primitive) {
+                    let values: Vec<String> = inner.iter().map(|v| format_scalar(v, opts)).collect();
+                    sink.write_str(&format!("- [{inner_n}{dsym}]: "))?;
+                    sink.write_str(&values.join(&delim_ch.to_string()))?;
+                } else {
+                    sink.write_str(&format!("- [{inner_n}{dsym}]:"))?;
+                    let nested_indent = " ".repeat((depth + 2) * opts.indent);
+                    for inner_item in inner {
+                        sink.newline();
+                        sink.write_str(&nested_indent)?;
+                        sink.write_str("- ")?;
+                        sink.write_str(&format_scalar(inner_item, opts))?;
+                    }
+                }
+            }
+            _ => {
+                sink.write_str(&child_indent)?;
+                sink.write_str("- ")?;
+                sink.write_str(&format_scalar(item, opts))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn node_get<'a>(fields: &'a [(String, Node)], key: &str) -> Option<&'a Node> {
+    fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+fn detect_tabular(arr: &[Node]) -> Option<Vec<String>> {
+    let first = match arr.first()? {
+        Node::Object(fields) => fields,
+        _ => return None,
+    };
+    let keys: Vec<String> = first.iter().map(|(k, _)| k.clone()).collect();
+    if keys.is_empty() || !first.iter().all(|(_, v)| is_primitive(v)) {
+        return None;
+    }
+    for item in &arr[1..] {
+        let obj = match item {
+            Node::Object(fields) => fields,
+            _ => return None,
+        };
+        if obj.len() != keys.len() {
+            return None;
+        }
+        for key in &keys {
+            if !is_primitive(node_get(obj, key)?) {
+                return None;
+            }
+        }
+    }
+    Some(keys)
+}
+
+fn is_primitive(node: &Node) -> bool {
+    matches!(node, Node::Null | Node::Bool(_) | Node::Number(_) | Node::String(_))
+}
+
+fn format_scalar(node: &Node, opts: &ToonOptions) -> String {
+    match node {
+        Node::Null => "null".to_string(),
+        Node::Bool(b) => b.to_string(),
+        Node::Number(n) => format_number(n, opts.raw_numbers),
+        Node::String(s) => {
+            if needs_quoting(s, opts.delimiter) {
+                format!("\"{}\"", escape_string(s))
+            } else {
+                s.clone()
+            }
+        }
+        Node::Array(_) | Node::Object(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn test_to_string_struct() {
+        let user = User { id: 1, name: "Alice".to_string() };
+        let out = to_string(&user, &ToonOptions::default()).unwrap();
+        assert_eq!(out, "id: 1\nname: Alice");
+    }
+
+    #[test]
+    fn test_to_string_tabular_vec() {
+        let users = vec![User { id: 1, name: "Alice".to_string() }, User { id: 2, name: "Bob".to_string() }];
+        let out = to_string(&users, &ToonOptions::default()).unwrap();
+        assert_eq!(out, "[2]{id,name}:\n  1,Alice\n  2,Bob");
+    }
+
+    #[test]
+    fn test_to_writer_matches_to_string() {
+        let user = User { id: 7, name: "Eve".to_string() };
+        let mut buf = Vec::new();
+        to_writer(&mut buf, &user, &ToonOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), to_string(&user, &ToonOptions::default()).unwrap());
+    }
+
+    #[test]
+    fn test_non_finite_float_errors() {
+        let result = to_string(&f64::NAN, &ToonOptions::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_encode_toon_agrees_with_to_string() {
+        let v = serde_json::json!({"a": [1, 2, 3], "b": {"c": true}});
+        let opts = ToonOptions::default();
+        assert_eq!(crate::encode_toon(&v, &opts), to_string(&v, &opts).unwrap());
+    }
+
+    #[test]
+    fn test_large_i64_is_exact() {
+        let out = to_string(&9_007_199_254_740_993i64, &ToonOptions::default()).unwrap();
+        assert_eq!(out, "9007199254740993");
+    }
+
+    // Only meaningful once the crate's own `arbitrary_precision` feature
+    // (forwarding to `serde_json/arbitrary_precision`) is wired up in
+    // Cargo.toml; absent that feature, `serde_json::Number` never emits the
+    // `ARBITRARY_PRECISION_NUMBER_TOKEN` sentinel this guards against.
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision_document_encodes_without_leaking_sentinel() {
+        let v: serde_json::Value = serde_json::from_str(
+            r#"{"age":30,"big":10000000000000000001,"pi":3.141592653589793238}"#,
+        )
+        .unwrap();
+
+        // Everyday numbers must still encode normally — the sentinel struct
+        // must not leak into the buffered `Node` tree as a literal object.
+        let out = to_string(&v, &ToonOptions::default()).unwrap();
+        assert!(out.contains("age: 30"), "sentinel leaked into output: {out}");
+
+        // With `raw_numbers`, the oversized/high-precision tokens survive
+        // exactly, since `Number` remembers its original text.
+        let opts = ToonOptions { raw_numbers: true, ..ToonOptions::default() };
+        let out = to_string(&v, &opts).unwrap();
+        assert!(out.contains("big: 10000000000000000001"));
+        assert!(out.contains("pi: 3.141592653589793238"));
+    }
+}